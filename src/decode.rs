@@ -0,0 +1,238 @@
+//! Reverses `encode::run`: demodulates a recorded SSTV WAV back into an
+//! image.
+//!
+//! SSTV carries luminance as an instantaneous frequency swept between 1500
+//! and 2300 Hz. We recover it by building an analytic signal (a Hilbert
+//! transform gives the quadrature component), taking the phase difference
+//! between consecutive samples, and mapping the result back through the
+//! inverse of `encode::Scans::push_pixel`'s `color_to_freq`.
+//!
+//! Unlike `encode::run`, this only understands one mode: the layout and
+//! timing below are `SstvMode::ScottieS1`'s, hardcoded rather than threaded
+//! through from a mode argument. Decoding a recording made with any other
+//! `--mode` will not produce a useful image.
+
+use hound::WavReader;
+use image::RgbImage;
+
+use crate::{
+    encode::TAU,
+    fir::{hamming, FIRFilter},
+    DecodeArgs,
+};
+
+/// `SstvMode::ScottieS1`'s fixed 320x256 layout and timing; see the module
+/// doc comment.
+const WIDTH: u32 = 320;
+const HEIGHT: u32 = 256;
+const PIXEL_DUR_MS: f64 = 0.432;
+const SEP_MS: f64 = 1.5;
+const PORCH_MS: f64 = 1.5;
+const SYNC_MS: f64 = 9.0;
+
+/// Odd-length antisymmetric Hilbert transformer taps, Hamming-windowed.
+fn hilbert_taps(len: usize) -> Vec<f32> {
+    assert!(len % 2 == 1, "hilbert transformer length must be odd");
+    let mid = (len / 2) as isize;
+    (0..len)
+        .map(|i| {
+            let n = i as isize - mid;
+            let window = hamming(i, len);
+            let h = if n % 2 == 0 {
+                0.0
+            } else {
+                2.0 / (std::f64::consts::PI * n as f64)
+            };
+            (h * window) as f32
+        })
+        .collect()
+}
+
+/// Windowed-sinc low-pass taps, used to clean up the I/Q components before
+/// the phase-difference step (it's noisy near the 1500-2300 Hz band edges
+/// otherwise).
+fn lowpass_taps(len: usize, cutoff_hz: f64, sample_rate: f64) -> Vec<f32> {
+    assert!(len % 2 == 1, "lowpass length must be odd");
+    let mid = (len / 2) as isize;
+    let fc = cutoff_hz / sample_rate;
+    (0..len)
+        .map(|i| {
+            let n = i as isize - mid;
+            let window = hamming(i, len);
+            let sinc = if n == 0 {
+                2.0 * fc
+            } else {
+                (2.0 * std::f64::consts::PI * fc * n as f64).sin() / (std::f64::consts::PI * n as f64)
+            };
+            (sinc * window) as f32
+        })
+        .collect()
+}
+
+/// Inverse of `encode::Scans::push_pixel`'s `color_to_freq`.
+fn freq_to_color(freq: f32) -> u8 {
+    (((freq - 1500.0) * 255.0 / 800.0).round() as i32).clamp(0, 255) as u8
+}
+
+pub fn run(args: DecodeArgs) {
+    let mut reader = WavReader::open(&args.wav_path).unwrap();
+    let spec = reader.spec();
+    let sample_rate = spec.sample_rate as f64;
+    let samples: Vec<f32> = match spec.sample_format {
+        hound::SampleFormat::Float => reader.samples::<f32>().map(|s| s.unwrap()).collect(),
+        hound::SampleFormat::Int => {
+            let max = (1i64 << (spec.bits_per_sample - 1)) as f32;
+            reader
+                .samples::<i32>()
+                .map(|s| s.unwrap() as f32 / max)
+                .collect()
+        }
+    };
+
+    let freqs = demodulate(&samples, sample_rate);
+    let image = slice_into_image(&freqs, sample_rate);
+    image.save(&args.out_path).unwrap();
+}
+
+/// Builds the analytic signal via a Hilbert FIR, low-pass filters it, then
+/// returns the instantaneous frequency (in hertz) at each input sample.
+fn demodulate(samples: &[f32], sample_rate: f64) -> Vec<f32> {
+    const HILBERT_TAPS: usize = 65;
+    const LOWPASS_TAPS: usize = 33;
+
+    let delay = HILBERT_TAPS / 2;
+    let imag: Vec<f32> =
+        FIRFilter::new(samples.iter().copied(), hilbert_taps(HILBERT_TAPS)).collect();
+    // The Hilbert FIR delays its output by `delay` samples; pad the real
+    // part so `real[n]`/`imag[n]` describe the same instant.
+    let real: Vec<f32> = std::iter::repeat_n(0.0, delay)
+        .chain(samples.iter().copied())
+        .collect();
+
+    let lp = lowpass_taps(LOWPASS_TAPS, 1100.0, sample_rate);
+    let i: Vec<f32> = FIRFilter::new(real.into_iter(), lp.clone()).collect();
+    let q: Vec<f32> = FIRFilter::new(imag.into_iter(), lp).collect();
+
+    let mut freqs = Vec::with_capacity(samples.len());
+    freqs.push(1500.0);
+    for n in 1..samples.len() {
+        let (re, im) = (i[n], q[n]);
+        let (pre, pim) = (i[n - 1], q[n - 1]);
+        // arg(z[n] * conj(z[n-1]))
+        let phase = (im * pre - re * pim).atan2(re * pre + im * pim);
+        freqs.push((phase as f64 * sample_rate / TAU) as f32);
+    }
+
+    median_filter(&freqs, (PIXEL_DUR_MS / 1000.0 * sample_rate) as usize | 1)
+}
+
+fn median_filter(data: &[f32], window: usize) -> Vec<f32> {
+    let half = window / 2;
+    (0..data.len())
+        .map(|n| {
+            let lo = n.saturating_sub(half);
+            let hi = (n + half + 1).min(data.len());
+            let mut w = data[lo..hi].to_vec();
+            w.sort_by(|a, b| a.partial_cmp(b).unwrap());
+            w[w.len() / 2]
+        })
+        .collect()
+}
+
+/// Finds the sample ranges of every 1200 Hz / ~9ms sync pulse.
+fn find_sync_pulses(freqs: &[f32], sample_rate: f64) -> Vec<(usize, usize)> {
+    let min_len = (SYNC_MS * 0.75 / 1000.0 * sample_rate) as usize;
+    let mut pulses = Vec::new();
+    let mut run_start = None;
+    for (n, &f) in freqs.iter().enumerate() {
+        if (f - 1200.0).abs() < 50.0 {
+            run_start.get_or_insert(n);
+        } else if let Some(start) = run_start.take() {
+            if n - start >= min_len {
+                pulses.push((start, n));
+            }
+        }
+    }
+    pulses
+}
+
+fn resample_segment(freqs: &[f32], start: usize, len: usize, pixels: usize) -> Vec<f32> {
+    (0..pixels)
+        .map(|px| {
+            let center = start + (px * len + len / 2) / pixels;
+            *freqs.get(center).unwrap_or(&1500.0)
+        })
+        .collect()
+}
+
+/// Recovers line timing from the sync pulses and slices each scanline into
+/// R/G/B segments using the same porch/separator layout `encode::run` emits,
+/// resampling each segment to `WIDTH` pixels.
+fn slice_into_image(freqs: &[f32], sample_rate: f64) -> RgbImage {
+    let ms = |m: f64| (m / 1000.0 * sample_rate) as usize;
+    let mut syncs = find_sync_pulses(freqs, sample_rate);
+    // The VIS header's 1200 Hz break pulse is detected too, and its 1200 Hz
+    // stop bit runs straight into the leading sync, making that one pulse far
+    // longer than `SYNC_MS`; drop everything before it so `syncs[0]` is the
+    // leading sync.
+    if let Some(lead) = syncs
+        .iter()
+        .rposition(|&(start, end)| end - start > ms(2.0 * SYNC_MS))
+    {
+        syncs.drain(..lead);
+    }
+    let (sep, porch) = (ms(SEP_MS), ms(PORCH_MS));
+    let scan_len = ms(PIXEL_DUR_MS) * WIDTH as usize;
+
+    let blank_row = || vec![1500.0f32; WIDTH as usize];
+    let mut green_rows = vec![blank_row(); HEIGHT as usize];
+    let mut blue_rows = vec![blank_row(); HEIGHT as usize];
+    let mut red_rows = vec![blank_row(); HEIGHT as usize];
+
+    // `encode::run` only emits a sync pulse once it has already written a row's
+    // green/blue scans (plus one leading sync before row 0), so the content
+    // between sync pulse `i` and `i + 1` is: [porch, red(i - 1)] (absent for
+    // i == 0), then [sep, green(i), sep, blue(i)].
+    for (i, pair) in syncs.windows(2).enumerate() {
+        let (_, gap_start) = pair[0];
+        let mut cursor = gap_start;
+        if i > 0 {
+            cursor += porch;
+            if i - 1 < HEIGHT as usize {
+                red_rows[i - 1] = resample_segment(freqs, cursor, scan_len, WIDTH as usize);
+            }
+            cursor += scan_len;
+        }
+        if i < HEIGHT as usize {
+            cursor += sep;
+            green_rows[i] = resample_segment(freqs, cursor, scan_len, WIDTH as usize);
+            cursor += scan_len + sep;
+            blue_rows[i] = resample_segment(freqs, cursor, scan_len, WIDTH as usize);
+        }
+    }
+    // The very last sync pulse is followed by a porch and a red scan with
+    // nothing after it (the file just ends there); like every sync `i`, it
+    // carries red(i - 1).
+    if let Some(&(_, last_end)) = syncs.last().filter(|_| syncs.len() >= 2) {
+        let row = syncs.len() - 2;
+        if row < HEIGHT as usize {
+            red_rows[row] = resample_segment(freqs, last_end + porch, scan_len, WIDTH as usize);
+        }
+    }
+
+    let mut image = RgbImage::new(WIDTH, HEIGHT);
+    for row in 0..HEIGHT as usize {
+        for col in 0..WIDTH as usize {
+            image.put_pixel(
+                col as u32,
+                row as u32,
+                image::Rgb([
+                    freq_to_color(red_rows[row][col]),
+                    freq_to_color(green_rows[row][col]),
+                    freq_to_color(blue_rows[row][col]),
+                ]),
+            );
+        }
+    }
+    image
+}
@@ -0,0 +1,251 @@
+use core::f32;
+use std::{
+    f64::consts::PI,
+    ops::{Add, Mul},
+};
+
+use hound::{WavSpec, WavWriter};
+use image::{imageops::resize, ImageReader, Rgba};
+
+use crate::{
+    fir::{bandpass_taps, FIRFilter},
+    mode::{rgb_to_ycbcr, Channel, ColorSpace, LineElement},
+    resample::resample,
+    EncodeArgs,
+};
+
+#[derive(Debug, Clone)]
+pub struct FreqDur {
+    /// Transmit a frequency (in hertz) for a certain duration (in milliseconds)
+    frequency: f32,
+    duration: f32,
+}
+pub const fn transmit(freq: f32, dur: f32) -> FreqDur {
+    FreqDur {
+        frequency: freq,
+        duration: dur,
+    }
+}
+const HEADER: &[FreqDur] = &[
+    transmit(500.0, 1000.0),
+    transmit(1900.0, 300.0),
+    transmit(1200.0, 10.0),
+    transmit(1900.0, 300.0),
+    transmit(1200.0, 10.0),
+];
+
+fn build_header(vis_code: u8, parity_even: bool) -> [FreqDur; 13] {
+    fn digital(bit: bool) -> FreqDur {
+        if bit {
+            transmit(1100.0, 30.0)
+        } else {
+            transmit(1300.0, 30.0)
+        }
+    }
+    std::array::from_fn(|idx| match idx {
+        0..=3 => HEADER[idx].clone(),
+        4..=10 => digital(((vis_code >> (idx - 4)) & 1) != 0),
+        11 => digital(parity_even),
+        12 => transmit(1200.0, 30.0),
+        _ => unreachable!(),
+    })
+}
+/// Three generic per-line sample buffers; which of R/G/B or Y/Cb/Cr each one
+/// holds is fixed by the mode's `ColorSpace`, and the mode's `line_template`
+/// decides the order they're transmitted in.
+pub struct Scans {
+    pixel_dur: f32,
+    a_samples: Vec<FreqDur>,
+    b_samples: Vec<FreqDur>,
+    c_samples: Vec<FreqDur>,
+}
+pub const TAU: f64 = PI * 2.0;
+impl Scans {
+    fn new(pixel_dur: f32) -> Self {
+        Self {
+            pixel_dur,
+            a_samples: Vec::new(),
+            b_samples: Vec::new(),
+            c_samples: Vec::new(),
+        }
+    }
+    fn clear(&mut self) {
+        self.a_samples.clear();
+        self.b_samples.clear();
+        self.c_samples.clear();
+    }
+    fn push_pixel(&mut self, pixel: &Rgba<u8>, chroma_partner: Option<Rgba<u8>>, space: ColorSpace) {
+        fn color_to_freq(col: u8) -> f32 {
+            (col as f32).mul((2300.0 - 1500.0) / 255.0).add(1500.0)
+        }
+        let (a, b, c) = match space {
+            ColorSpace::Rgb => (pixel.0[0], pixel.0[1], pixel.0[2]),
+            ColorSpace::YCbCr => {
+                let (y, cb, cr) = rgb_to_ycbcr(pixel);
+                match chroma_partner {
+                    Some(partner) => {
+                        let (_, pcb, pcr) = rgb_to_ycbcr(&partner);
+                        (
+                            y,
+                            ((cb as u16 + pcb as u16) / 2) as u8,
+                            ((cr as u16 + pcr as u16) / 2) as u8,
+                        )
+                    }
+                    None => (y, cb, cr),
+                }
+            }
+        };
+        self.a_samples.push(transmit(color_to_freq(a), self.pixel_dur));
+        self.b_samples.push(transmit(color_to_freq(b), self.pixel_dur));
+        self.c_samples.push(transmit(color_to_freq(c), self.pixel_dur));
+    }
+}
+
+pub fn run(args: EncodeArgs) {
+    let mode = args.mode;
+    let mut buf = Vec::new();
+    let image = ImageReader::open(args.image).unwrap().decode().unwrap();
+    let scaled = resize(
+        &image,
+        mode.width(),
+        mode.height(),
+        image::imageops::FilterType::Gaussian,
+    );
+    let header = build_header(mode.vis_code(), mode.parity_even());
+
+    buf.extend_from_slice(&header);
+
+    if mode.leading_sync() {
+        buf.push(transmit(1200.0, mode.sync_dur_ms()));
+    }
+
+    let mut samples = Scans::new(mode.pixel_dur_ms());
+    for row in 0..mode.height() {
+        let chroma_partner_row = mode.chroma_partner_row(row);
+        for col in 0..mode.width() {
+            let pixel = scaled.get_pixel(col, row);
+            let chroma_partner = chroma_partner_row.map(|r| *scaled.get_pixel(col, r));
+            samples.push_pixel(pixel, chroma_partner, mode.color_space());
+        }
+        for element in mode.line_template(row) {
+            match element {
+                LineElement::Sync => buf.push(transmit(1200.0, mode.sync_dur_ms())),
+                LineElement::Porch => buf.push(transmit(1500.0, mode.porch_dur_ms())),
+                LineElement::Separator => buf.push(transmit(1500.0, mode.separator_dur_ms())),
+                LineElement::Scan(Channel::A) => buf.extend_from_slice(&samples.a_samples),
+                LineElement::Scan(Channel::B) => buf.extend_from_slice(&samples.b_samples),
+                LineElement::Scan(Channel::C) => buf.extend_from_slice(&samples.c_samples),
+            }
+        }
+        samples.clear();
+    }
+
+    let pilot = args
+        .pilot_frequency
+        .map(|frequency| Pilot { frequency, amplitude: args.pilot_amplitude });
+    let taps = bandpass_taps(
+        args.filter_taps,
+        args.filter_low_hz,
+        args.filter_high_hz,
+        SYNTH_RATE as f64,
+    );
+    let synthesized = render(buf, SYNTH_RATE, pilot, taps);
+    let resampled = resample(
+        &synthesized,
+        SYNTH_RATE as f64,
+        args.sample_rate as f64,
+        args.interpolation,
+    );
+
+    let spec = WavSpec {
+        channels: 1,
+        sample_rate: args.sample_rate,
+        bits_per_sample: 32,
+        sample_format: hound::SampleFormat::Float,
+    };
+    let mut writer = WavWriter::create(args.out_path, spec).unwrap();
+    for sample in resampled {
+        writer.write_sample(sample).unwrap();
+    }
+    writer.finalize().unwrap();
+}
+
+/// Tones are synthesized at this fixed rate so their frequencies stay exact
+/// regardless of the requested output `--sample-rate`; `resample` then
+/// converts to the requested rate.
+const SYNTH_RATE: u32 = 11025 * 4;
+
+/// A constant-frequency tone mixed in alongside the modulated signal, e.g. a
+/// pilot tone for a receiver's AFC to lock onto. Tracked with its own phase
+/// accumulator so it stays continuous independent of the scan tones.
+#[derive(Debug, Clone, Copy)]
+pub struct Pilot {
+    pub frequency: f32,
+    pub amplitude: f32,
+}
+
+/// A phase-continuous FM oscillator: `phase` is integrated sample-by-sample
+/// (`phase += TAU * frequency * dt`) rather than recomputed from an absolute
+/// time, so the waveform doesn't click when the frequency changes between
+/// `FreqDur`s.
+struct Oscillator {
+    items: std::vec::IntoIter<FreqDur>,
+    current: Option<(f64, u32)>,
+    dt: f64,
+    phase: f64,
+    pilot: Option<Pilot>,
+    pilot_phase: f64,
+}
+
+impl Oscillator {
+    fn new(items: Vec<FreqDur>, sample_rate: u32, pilot: Option<Pilot>) -> Self {
+        Self {
+            items: items.into_iter(),
+            current: None,
+            dt: 1.0 / sample_rate as f64,
+            phase: 0.0,
+            pilot,
+            pilot_phase: 0.0,
+        }
+    }
+}
+
+impl Iterator for Oscillator {
+    type Item = f32;
+    fn next(&mut self) -> Option<f32> {
+        loop {
+            match self.current {
+                Some((_, 0)) => self.current = None,
+                Some((frequency, remaining)) => {
+                    self.current = Some((frequency, remaining - 1));
+                    let mut sample = (self.phase.sin() * 0.8) as f32;
+                    if let Some(pilot) = self.pilot {
+                        sample += (self.pilot_phase.sin() as f32) * pilot.amplitude;
+                        self.pilot_phase =
+                            (self.pilot_phase + TAU * pilot.frequency as f64 * self.dt) % TAU;
+                    }
+                    self.phase = (self.phase + TAU * frequency * self.dt) % TAU;
+                    return Some(sample);
+                }
+                None => {
+                    let item = self.items.next()?;
+                    let remaining = (item.duration as f64 / 1000.0 / self.dt) as u32;
+                    self.current = Some((item.frequency as f64, remaining));
+                }
+            }
+        }
+    }
+}
+
+/// Synthesizes `items` at `sample_rate`, band-limiting the oscillator's
+/// output with a FIR filter so abrupt amplitude/frequency transitions don't
+/// spread energy outside the SSTV band.
+fn render(
+    items: Vec<FreqDur>,
+    sample_rate: u32,
+    pilot: Option<Pilot>,
+    filter_taps: Vec<f32>,
+) -> Vec<f32> {
+    let oscillator = Oscillator::new(items, sample_rate, pilot);
+    FIRFilter::new(oscillator, filter_taps).collect()
+}
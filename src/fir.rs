@@ -0,0 +1,67 @@
+//! A small streaming FIR filter, shared by the encoder's band-limiting
+//! stage and the decoder's Hilbert/low-pass stages.
+
+/// A streaming FIR filter implemented as an `Iterator` adapter: a circular
+/// `state` buffer holds the last `coeffs.len()` inputs, and each pulled
+/// sample is `sum(state[pos - i] * coeffs[i])`.
+pub struct FIRFilter<I> {
+    inner: I,
+    coeffs: Vec<f32>,
+    state: Vec<f32>,
+    pos: usize,
+}
+
+impl<I> FIRFilter<I> {
+    pub fn new(inner: I, coeffs: Vec<f32>) -> Self {
+        let len = coeffs.len();
+        Self {
+            inner,
+            coeffs,
+            state: vec![0.0; len],
+            pos: 0,
+        }
+    }
+}
+
+impl<I: Iterator<Item = f32>> Iterator for FIRFilter<I> {
+    type Item = f32;
+    fn next(&mut self) -> Option<f32> {
+        let sample = self.inner.next()?;
+        let len = self.coeffs.len();
+        self.state[self.pos] = sample;
+        let acc = self
+            .coeffs
+            .iter()
+            .enumerate()
+            .map(|(i, coeff)| self.state[(self.pos + len - i) % len] * coeff)
+            .sum();
+        self.pos = (self.pos + 1) % len;
+        Some(acc)
+    }
+}
+
+pub fn hamming(i: usize, len: usize) -> f64 {
+    0.54 - 0.46 * (std::f64::consts::PI * 2.0 * i as f64 / (len - 1) as f64).cos()
+}
+
+/// Hamming-windowed band-pass coefficients (difference of two windowed-sinc
+/// low-passes), for band-limiting the rendered audio to the SSTV band.
+pub fn bandpass_taps(len: usize, low_hz: f64, high_hz: f64, sample_rate: f64) -> Vec<f32> {
+    assert!(len % 2 == 1, "bandpass filter length must be odd");
+    let mid = (len / 2) as isize;
+    let (fl, fh) = (low_hz / sample_rate, high_hz / sample_rate);
+    (0..len)
+        .map(|i| {
+            let n = i as isize - mid;
+            let ideal = if n == 0 {
+                2.0 * (fh - fl)
+            } else {
+                let n = n as f64;
+                ((2.0 * std::f64::consts::PI * fh * n).sin()
+                    - (2.0 * std::f64::consts::PI * fl * n).sin())
+                    / (std::f64::consts::PI * n)
+            };
+            (ideal * hamming(i, len)) as f32
+        })
+        .collect()
+}
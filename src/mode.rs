@@ -0,0 +1,208 @@
+//! SSTV mode definitions: VIS code, image dimensions, timing, scan order and
+//! color space for each supported mode.
+//!
+//! An encoded line is described as a small sequence of [`LineElement`]s
+//! ([`Scans::push_pixel`](crate::encode::Scans::push_pixel) fills three
+//! generic channel buffers, `A`/`B`/`C`, per mode's [`ColorSpace`]); `encode::run`'s
+//! per-row loop just walks that sequence and emits the matching tone.
+
+use image::Rgba;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum SstvMode {
+    MartinM1,
+    MartinM2,
+    ScottieS1,
+    ScottieS2,
+    ScottieDx,
+    Robot36,
+    Robot72,
+    Pd90,
+    Pd120,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColorSpace {
+    /// Channel A/B/C carry R/G/B directly.
+    Rgb,
+    /// Channel A/B/C carry Y/Cb/Cr.
+    YCbCr,
+}
+
+/// Which of `Scans`' three generic sample buffers a `Scan` element reads
+/// from; the mapping of R/G/B or Y/Cb/Cr onto A/B/C is fixed (see
+/// `ColorSpace`), so a mode's scan order is just the order it lists these.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Channel {
+    A,
+    B,
+    C,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum LineElement {
+    Sync,
+    Porch,
+    Separator,
+    Scan(Channel),
+}
+
+impl SstvMode {
+    pub fn vis_code(self) -> u8 {
+        match self {
+            SstvMode::MartinM1 => 44,
+            SstvMode::MartinM2 => 40,
+            SstvMode::ScottieS1 => 60,
+            SstvMode::ScottieS2 => 56,
+            SstvMode::ScottieDx => 76,
+            SstvMode::Robot36 => 8,
+            SstvMode::Robot72 => 12,
+            SstvMode::Pd90 => 99,
+            SstvMode::Pd120 => 95,
+        }
+    }
+
+    /// Even parity over the VIS code's 7 data bits, as transmitted in the
+    /// header's parity bit.
+    pub fn parity_even(self) -> bool {
+        !self.vis_code().count_ones().is_multiple_of(2)
+    }
+
+    pub fn width(self) -> u32 {
+        320
+    }
+
+    pub fn height(self) -> u32 {
+        match self {
+            SstvMode::MartinM1
+            | SstvMode::MartinM2
+            | SstvMode::ScottieS1
+            | SstvMode::ScottieS2
+            | SstvMode::ScottieDx => 256,
+            SstvMode::Robot36 | SstvMode::Robot72 | SstvMode::Pd90 | SstvMode::Pd120 => 240,
+        }
+    }
+
+    pub fn color_space(self) -> ColorSpace {
+        match self {
+            SstvMode::MartinM1
+            | SstvMode::MartinM2
+            | SstvMode::ScottieS1
+            | SstvMode::ScottieS2
+            | SstvMode::ScottieDx => ColorSpace::Rgb,
+            SstvMode::Robot36 | SstvMode::Robot72 | SstvMode::Pd90 | SstvMode::Pd120 => {
+                ColorSpace::YCbCr
+            }
+        }
+    }
+
+    /// Whether the B/C (chroma) channels are only transmitted every other
+    /// row, with the value shared across the pair - i.e. 4:2:0-style
+    /// subsampling.
+    pub fn chroma_subsampled(self) -> bool {
+        matches!(self, SstvMode::Robot36 | SstvMode::Pd90 | SstvMode::Pd120)
+    }
+
+    pub fn pixel_dur_ms(self) -> f32 {
+        match self {
+            SstvMode::MartinM1 => 0.4576,
+            SstvMode::MartinM2 => 0.2288,
+            SstvMode::ScottieS1 => 0.4320,
+            SstvMode::ScottieS2 => 0.2752,
+            SstvMode::ScottieDx => 1.0848,
+            SstvMode::Robot36 => 0.2933,
+            SstvMode::Robot72 => 0.4400,
+            SstvMode::Pd90 => 0.5320,
+            SstvMode::Pd120 => 0.7100,
+        }
+    }
+
+    pub fn sync_dur_ms(self) -> f32 {
+        match self {
+            SstvMode::MartinM1 | SstvMode::MartinM2 => 4.862,
+            SstvMode::ScottieS1 | SstvMode::ScottieS2 | SstvMode::ScottieDx => 9.0,
+            SstvMode::Robot36 | SstvMode::Robot72 => 9.0,
+            SstvMode::Pd90 | SstvMode::Pd120 => 20.0,
+        }
+    }
+
+    pub fn porch_dur_ms(self) -> f32 {
+        match self {
+            SstvMode::MartinM1 | SstvMode::MartinM2 => 0.572,
+            SstvMode::ScottieS1 | SstvMode::ScottieS2 | SstvMode::ScottieDx => 1.5,
+            SstvMode::Robot36 | SstvMode::Robot72 => 3.0,
+            SstvMode::Pd90 | SstvMode::Pd120 => 2.08,
+        }
+    }
+
+    pub fn separator_dur_ms(self) -> f32 {
+        match self {
+            SstvMode::MartinM1 | SstvMode::MartinM2 => 0.572,
+            SstvMode::ScottieS1 | SstvMode::ScottieS2 | SstvMode::ScottieDx => 1.5,
+            SstvMode::Robot36 | SstvMode::Robot72 => 4.5,
+            SstvMode::Pd90 | SstvMode::Pd120 => 0.0,
+        }
+    }
+
+    /// Scottie modes transmit one 9ms sync pulse before the very first
+    /// scanline, outside the per-row template (every later sync is emitted
+    /// mid-row, see `line_template`).
+    pub fn leading_sync(self) -> bool {
+        matches!(
+            self,
+            SstvMode::ScottieS1 | SstvMode::ScottieS2 | SstvMode::ScottieDx
+        )
+    }
+
+    /// The sequence of sync/porch/separator/scan elements transmitted for
+    /// image row `row`.
+    pub fn line_template(self, row: u32) -> Vec<LineElement> {
+        use Channel::{A, B, C};
+        use LineElement::{Porch, Scan, Separator, Sync};
+        match self {
+            SstvMode::MartinM1 | SstvMode::MartinM2 => {
+                vec![Sync, Porch, Scan(B), Separator, Scan(C), Separator, Scan(A), Separator]
+            }
+            SstvMode::ScottieS1 | SstvMode::ScottieS2 | SstvMode::ScottieDx => {
+                vec![Separator, Scan(B), Separator, Scan(C), Sync, Porch, Scan(A)]
+            }
+            SstvMode::Robot72 => {
+                vec![Sync, Porch, Scan(A), Separator, Scan(B), Separator, Scan(C)]
+            }
+            SstvMode::Robot36 => {
+                let chroma = if row.is_multiple_of(2) { Scan(B) } else { Scan(C) };
+                vec![Sync, Porch, Scan(A), Separator, chroma]
+            }
+            SstvMode::Pd90 | SstvMode::Pd120 => {
+                let chroma = if row.is_multiple_of(2) { Scan(B) } else { Scan(C) };
+                vec![Sync, Porch, Scan(A), chroma]
+            }
+        }
+    }
+
+    /// If this mode averages chroma across a pair of rows before
+    /// subsampling, the row index of the partner to average with.
+    pub fn chroma_partner_row(self, row: u32) -> Option<u32> {
+        if !self.chroma_subsampled() {
+            return None;
+        }
+        Some(if row.is_multiple_of(2) {
+            (row + 1).min(self.height() - 1)
+        } else {
+            row - 1
+        })
+    }
+}
+
+/// ITU-R BT.601 (full-range) RGB -> YCbCr conversion.
+pub fn rgb_to_ycbcr(pixel: &Rgba<u8>) -> (u8, u8, u8) {
+    let [r, g, b, _] = pixel.0.map(f32::from);
+    let y = 0.299 * r + 0.587 * g + 0.114 * b;
+    let cb = 128.0 + (b - y) * 0.564;
+    let cr = 128.0 + (r - y) * 0.713;
+    (
+        y.clamp(0.0, 255.0) as u8,
+        cb.clamp(0.0, 255.0) as u8,
+        cr.clamp(0.0, 255.0) as u8,
+    )
+}
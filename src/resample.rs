@@ -0,0 +1,97 @@
+//! Resamples a fixed-rate buffer of synthesized samples to an arbitrary
+//! output rate, so `encode::run` can synthesize tones at a rate that keeps
+//! their frequencies exact and let the user pick any WAV sample rate
+//! independently.
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum Interpolation {
+    Nearest,
+    Linear,
+    Cubic,
+    Sinc,
+}
+
+/// Resamples `input` (sampled at `in_rate` Hz) to `out_rate` Hz.
+pub fn resample(input: &[f32], in_rate: f64, out_rate: f64, interpolation: Interpolation) -> Vec<f32> {
+    let ratio = in_rate / out_rate;
+    // When downsampling, the sinc kernel's cutoff must shrink (and its
+    // support widen) by the same factor, or frequencies above the new
+    // Nyquist rate alias back into the output instead of being filtered out.
+    let cutoff_scale = (out_rate / in_rate).min(1.0);
+    let out_len = (input.len() as f64 / ratio) as usize;
+    (0..out_len)
+        .map(|n| {
+            let pos = n as f64 * ratio;
+            match interpolation {
+                Interpolation::Nearest => nearest(input, pos),
+                Interpolation::Linear => linear(input, pos),
+                Interpolation::Cubic => cubic(input, pos),
+                Interpolation::Sinc => windowed_sinc(input, pos, cutoff_scale),
+            }
+        })
+        .collect()
+}
+
+fn sample_at(input: &[f32], i: isize) -> f32 {
+    if i < 0 {
+        0.0
+    } else {
+        input.get(i as usize).copied().unwrap_or(0.0)
+    }
+}
+
+fn nearest(input: &[f32], pos: f64) -> f32 {
+    sample_at(input, pos.round() as isize)
+}
+
+fn linear(input: &[f32], pos: f64) -> f32 {
+    let i0 = pos.floor() as isize;
+    let frac = (pos - i0 as f64) as f32;
+    sample_at(input, i0) * (1.0 - frac) + sample_at(input, i0 + 1) * frac
+}
+
+/// Catmull-Rom cubic spline through the four samples surrounding `pos`.
+fn cubic(input: &[f32], pos: f64) -> f32 {
+    let i1 = pos.floor() as isize;
+    let t = (pos - i1 as f64) as f32;
+    let (p0, p1, p2, p3) = (
+        sample_at(input, i1 - 1),
+        sample_at(input, i1),
+        sample_at(input, i1 + 1),
+        sample_at(input, i1 + 2),
+    );
+    let a0 = -0.5 * p0 + 1.5 * p1 - 1.5 * p2 + 0.5 * p3;
+    let a1 = p0 - 2.5 * p1 + 2.0 * p2 - 0.5 * p3;
+    let a2 = -0.5 * p0 + 0.5 * p2;
+    let a3 = p1;
+    ((a0 * t + a1) * t + a2) * t + a3
+}
+
+const SINC_TAPS: isize = 8;
+
+/// A Hann-windowed sinc kernel over the samples surrounding `pos`, evaluated
+/// directly (no precomputed coefficients, since `pos` falls at a different
+/// fractional offset from the nearest input sample every output sample).
+/// `cutoff_scale` is `min(1.0, out_rate / in_rate)`: at 1.0 this is a plain
+/// interpolating sinc spanning `2 * SINC_TAPS + 1` samples; below 1.0 (i.e.
+/// downsampling) the cutoff and support both shrink/widen by that factor so
+/// the kernel also band-limits the signal to the new Nyquist rate.
+fn windowed_sinc(input: &[f32], pos: f64, cutoff_scale: f64) -> f32 {
+    let half_width = (SINC_TAPS as f64 / cutoff_scale).ceil() as isize;
+    let center = pos.floor() as isize;
+    let mut acc = 0.0f64;
+    for k in -half_width..=half_width {
+        let i = center + k;
+        let d = pos - i as f64;
+        let x = d * cutoff_scale;
+        let sinc = if x.abs() < 1e-9 {
+            1.0
+        } else {
+            let px = std::f64::consts::PI * x;
+            px.sin() / px
+        };
+        let window = 0.5 + 0.5 * (std::f64::consts::PI * d / half_width as f64).cos();
+        acc += sample_at(input, i) as f64 * sinc * cutoff_scale * window;
+    }
+    acc as f32
+}